@@ -0,0 +1,277 @@
+// Copyright (C) 2016-2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::ptr;
+use std::mem;
+use std::ffi::CString;
+use std::ops::{Deref, DerefMut};
+use std::borrow::{Borrow, BorrowMut, ToOwned};
+use std::marker::PhantomData;
+
+use structure::Structure;
+
+use glib;
+use gst;
+
+pub struct OwnedCaps(*mut Caps, PhantomData<Caps>);
+
+// SAFETY: every structure reachable through `Caps`/`OwnedCaps` is subject
+// to the exact same contract `Structure`'s own `Send`/`Sync` impl relies
+// on (see the safety comment on `Structure` in structure.rs): fields are only
+// ever inserted through `Structure::set`'s `SendValue` bound, so an
+// `OwnedCaps`/`Caps` built exclusively through `new_empty`/`new_any` and
+// `Structure`'s safe API owns no thread-affine data and is safe to move or
+// share across threads. `from_string` (see its doc comment) and
+// `Structure::set_unchecked` sidestep that bound and are marked `unsafe`
+// accordingly.
+unsafe impl Send for OwnedCaps {}
+unsafe impl Sync for OwnedCaps {}
+
+impl OwnedCaps {
+    pub fn new_empty() -> OwnedCaps {
+        OwnedCaps(
+            unsafe { gst::gst_caps_new_empty() as *mut Caps },
+            PhantomData,
+        )
+    }
+
+    pub fn new_any() -> OwnedCaps {
+        OwnedCaps(
+            unsafe { gst::gst_caps_new_any() as *mut Caps },
+            PhantomData,
+        )
+    }
+
+    /// Parses `s` into caps.
+    ///
+    /// # Safety
+    ///
+    /// Like [`OwnedStructure::from_string`](../structure/struct.OwnedStructure.html#method.from_string),
+    /// `gst_caps_from_string` accepts arbitrary serialized structures, so
+    /// the parsed fields aren't guaranteed to be `SendValue`-safe. The
+    /// caller must ensure `s` only ever serializes fields that are safe to
+    /// send to another thread before relying on the returned `OwnedCaps`'s
+    /// `Send`/`Sync` impls.
+    pub unsafe fn from_string(s: &str) -> Option<OwnedCaps> {
+        let cstr = CString::new(s).unwrap();
+        let caps = gst::gst_caps_from_string(cstr.as_ptr());
+        if caps.is_null() {
+            None
+        } else {
+            Some(OwnedCaps(caps as *mut Caps, PhantomData))
+        }
+    }
+
+    pub unsafe fn into_ptr(self) -> *mut gst::GstCaps {
+        let ptr = self.0 as *mut Caps as *mut gst::GstCaps;
+        mem::forget(self);
+
+        ptr
+    }
+}
+
+impl Deref for OwnedCaps {
+    type Target = Caps;
+
+    fn deref(&self) -> &Caps {
+        unsafe { &*self.0 }
+    }
+}
+
+impl DerefMut for OwnedCaps {
+    fn deref_mut(&mut self) -> &mut Caps {
+        unsafe { &mut *self.0 }
+    }
+}
+
+impl AsRef<Caps> for OwnedCaps {
+    fn as_ref(&self) -> &Caps {
+        self.deref()
+    }
+}
+
+impl AsMut<Caps> for OwnedCaps {
+    fn as_mut(&mut self) -> &mut Caps {
+        self.deref_mut()
+    }
+}
+
+impl Clone for OwnedCaps {
+    fn clone(&self) -> Self {
+        OwnedCaps(
+            unsafe { gst::gst_caps_copy(&(*self.0).0) as *mut Caps },
+            PhantomData,
+        )
+    }
+}
+
+impl Drop for OwnedCaps {
+    fn drop(&mut self) {
+        unsafe { gst::gst_caps_unref(&mut (*self.0).0) }
+    }
+}
+
+impl fmt::Debug for OwnedCaps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_string())
+    }
+}
+
+impl PartialEq for OwnedCaps {
+    fn eq(&self, other: &OwnedCaps) -> bool {
+        self.as_ref().eq(other)
+    }
+}
+
+impl PartialEq<Caps> for OwnedCaps {
+    fn eq(&self, other: &Caps) -> bool {
+        self.as_ref().eq(other)
+    }
+}
+
+impl Eq for OwnedCaps {}
+
+impl Borrow<Caps> for OwnedCaps {
+    fn borrow(&self) -> &Caps {
+        unsafe { &*self.0 }
+    }
+}
+
+impl BorrowMut<Caps> for OwnedCaps {
+    fn borrow_mut(&mut self) -> &mut Caps {
+        unsafe { &mut *self.0 }
+    }
+}
+
+impl ToOwned for Caps {
+    type Owned = OwnedCaps;
+
+    fn to_owned(&self) -> OwnedCaps {
+        OwnedCaps(
+            unsafe { gst::gst_caps_copy(&self.0) as *mut Caps },
+            PhantomData,
+        )
+    }
+}
+
+#[repr(C)]
+pub struct Caps(gst::GstCaps);
+
+// SAFETY: see the safety comment on `OwnedCaps`'s `Send`/`Sync` impl above.
+unsafe impl Send for Caps {}
+unsafe impl Sync for Caps {}
+
+impl Caps {
+    pub unsafe fn from_borrowed_ptr<'a>(ptr: *const gst::GstCaps) -> &'a Caps {
+        assert!(!ptr.is_null());
+
+        &*(ptr as *mut Caps)
+    }
+
+    pub unsafe fn from_borrowed_mut_ptr<'a>(ptr: *mut gst::GstCaps) -> &'a mut Caps {
+        assert!(!ptr.is_null());
+
+        &mut *(ptr as *mut Caps)
+    }
+
+    pub fn to_string(&self) -> String {
+        unsafe {
+            let ptr = gst::gst_caps_to_string(&self.0);
+            let s = ::std::ffi::CStr::from_ptr(ptr).to_str().unwrap().into();
+            glib::g_free(ptr as glib::gpointer);
+
+            s
+        }
+    }
+
+    pub fn n_structures(&self) -> u32 {
+        unsafe { gst::gst_caps_get_size(&self.0) as u32 }
+    }
+
+    /// Returns the structure at `idx`, or `None` if `idx` is past the
+    /// number of structures in these caps.
+    ///
+    /// The returned reference borrows from `self`, so it can't outlive
+    /// (and therefore can't dangle relative to) the caps it came from.
+    pub fn get_structure(&self, idx: u32) -> Option<&Structure> {
+        unsafe {
+            let ptr = gst::gst_caps_get_structure(&self.0, idx);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Structure::from_borrowed_ptr(ptr))
+            }
+        }
+    }
+
+    /// Mutable counterpart of [`get_structure`](#method.get_structure).
+    pub fn get_mut_structure(&mut self, idx: u32) -> Option<&mut Structure> {
+        unsafe {
+            let ptr = gst::gst_caps_get_structure(&self.0, idx) as *mut gst::GstStructure;
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Structure::from_borrowed_mut_ptr(ptr))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Caps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_string())
+    }
+}
+
+impl PartialEq for Caps {
+    fn eq(&self, other: &Caps) -> bool {
+        (unsafe { gst::gst_caps_is_equal(&self.0, &other.0) } == glib::GTRUE)
+    }
+}
+
+impl Eq for Caps {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_structure() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        // Safety: the literal below only serializes plain integer fields.
+        let caps =
+            unsafe { OwnedCaps::from_string("video/x-raw, width=(int)1920, height=(int)1080") }
+                .unwrap();
+
+        let s = caps.get_structure(0).unwrap();
+        assert_eq!(s.get::<i32>("width").unwrap().get(), 1920i32);
+        assert_eq!(s.get::<i32>("height").unwrap().get(), 1080i32);
+
+        assert!(caps.get_structure(1).is_none());
+    }
+
+    #[test]
+    fn get_mut_structure() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        // Safety: the literal below only serializes a plain integer field.
+        let mut caps = unsafe { OwnedCaps::from_string("video/x-raw, width=(int)1920") }.unwrap();
+
+        {
+            let s = caps.get_mut_structure(0).unwrap();
+            s.set("width", 1280i32);
+        }
+
+        assert_eq!(
+            caps.get_structure(0).unwrap().get::<i32>("width").unwrap().get(),
+            1280i32
+        );
+    }
+}
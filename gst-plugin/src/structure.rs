@@ -19,8 +19,34 @@ use value::*;
 use glib;
 use gst;
 
+/// Error returned by [`Structure::get_typed`](struct.Structure.html#method.get_typed),
+/// distinguishing a field that is absent from one that is present but has
+/// the wrong type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GetError<'a, E> {
+    FieldNotFound { name: &'a str },
+    ValueGetError { name: &'a str, error: E },
+}
+
+impl<'a, E: fmt::Display> fmt::Display for GetError<'a, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GetError::FieldNotFound { name } => write!(f, "field `{}` not found", name),
+            GetError::ValueGetError { name, ref error } => {
+                write!(f, "field `{}` has wrong type: {}", name, error)
+            }
+        }
+    }
+}
+
 pub struct OwnedStructure(*mut Structure, PhantomData<Structure>);
 
+// SAFETY: `OwnedStructure` uniquely owns the `Structure` it points to (see
+// `Structure`'s own `Send`/`Sync` impl for why a `Structure` is safe to
+// share/move between threads in the first place).
+unsafe impl Send for OwnedStructure {}
+unsafe impl Sync for OwnedStructure {}
+
 impl OwnedStructure {
     pub fn new_empty(name: &str) -> OwnedStructure {
         let name_cstr = CString::new(name).unwrap();
@@ -30,25 +56,34 @@ impl OwnedStructure {
         )
     }
 
-    pub fn new(name: &str, values: &[(&str, Value)]) -> OwnedStructure {
+    pub fn new(name: &str, values: &[(&str, SendableValue)]) -> OwnedStructure {
         let mut structure = OwnedStructure::new_empty(name);
 
         for &(f, ref v) in values {
-            structure.set(f, v.clone());
+            // A `SendableValue` can only be built from a `SendValue`.
+            unsafe { structure.set_unchecked(f, v.clone()) };
         }
 
         structure
     }
 
-    pub fn from_string(s: &str) -> Option<OwnedStructure> {
-        unsafe {
-            let cstr = CString::new(s).unwrap();
-            let structure = gst::gst_structure_from_string(cstr.as_ptr(), ptr::null_mut());
-            if structure.is_null() {
-                None
-            } else {
-                Some(OwnedStructure(structure as *mut Structure, PhantomData))
-            }
+    /// Parses `s` into a structure.
+    ///
+    /// # Safety
+    ///
+    /// `gst_structure_from_string` accepts arbitrary serialized field
+    /// types, including ones registered by code this crate doesn't control,
+    /// so the resulting fields aren't guaranteed to be `SendValue`-safe.
+    /// The caller must ensure `s` only ever serializes fields that are safe
+    /// to send to another thread before relying on the returned
+    /// `OwnedStructure`'s `Send`/`Sync` impls.
+    pub unsafe fn from_string(s: &str) -> Option<OwnedStructure> {
+        let cstr = CString::new(s).unwrap();
+        let structure = gst::gst_structure_from_string(cstr.as_ptr(), ptr::null_mut());
+        if structure.is_null() {
+            None
+        } else {
+            Some(OwnedStructure(structure as *mut Structure, PhantomData))
         }
     }
 
@@ -58,6 +93,42 @@ impl OwnedStructure {
 
         ptr
     }
+
+    pub fn builder(name: &str) -> Builder {
+        Builder::new(name)
+    }
+}
+
+/// Chaining builder for [`OwnedStructure`](struct.OwnedStructure.html).
+///
+/// Allows constructing a structure field by field without having to
+/// pre-box every value into a `Value` and collect them into a slice:
+///
+/// ```ignore
+/// let s = OwnedStructure::builder("video/x-raw")
+///     .field("width", 1920i32)
+///     .field("height", 1080i32)
+///     .build();
+/// ```
+pub struct Builder {
+    structure: OwnedStructure,
+}
+
+impl Builder {
+    fn new(name: &str) -> Builder {
+        Builder {
+            structure: OwnedStructure::new_empty(name),
+        }
+    }
+
+    pub fn field<T: SendValue>(mut self, name: &str, value: T) -> Self {
+        self.structure.set(name, value);
+        self
+    }
+
+    pub fn build(self) -> OwnedStructure {
+        self.structure
+    }
 }
 
 impl Deref for OwnedStructure {
@@ -147,6 +218,16 @@ impl ToOwned for Structure {
 #[repr(C)]
 pub struct Structure(gst::GstStructure);
 
+// SAFETY: `set` only accepts `SendValue`s, so every field stored in a
+// `Structure` is guaranteed to own its data rather than borrow from
+// thread-local state. `get_value`/`get` hand out `ValueRef`s whose lifetime
+// is tied to the `&Structure` they were obtained from, so a reference can
+// never outlive (and therefore never alias across threads with) the
+// structure it borrows from. That makes it sound for a `Structure`, and
+// therefore an `OwnedStructure`, to move between threads.
+unsafe impl Send for Structure {}
+unsafe impl Sync for Structure {}
+
 impl Structure {
     pub unsafe fn from_borrowed_ptr<'a>(ptr: *const gst::GstStructure) -> &'a Structure {
         assert!(!ptr.is_null());
@@ -171,7 +252,29 @@ impl Structure {
     }
 
     pub fn get<'a, T: ValueType<'a>>(&'a self, name: &str) -> Option<TypedValueRef<'a, T>> {
-        self.get_value(name).and_then(TypedValueRef::from_value_ref)
+        self.get_typed(name).ok().map(TypedValueRef::new)
+    }
+
+    /// Gets the value of field `name`, distinguishing a missing field from
+    /// a field that is present but doesn't have the requested type.
+    pub fn get_typed<'a, 'b, T: ValueType<'a>>(
+        &'a self,
+        name: &'b str,
+    ) -> Result<T, GetError<'b, ValueTypeMismatchError>> {
+        match self.get_value(name) {
+            None => Err(GetError::FieldNotFound { name: name }),
+            Some(value) => {
+                let actual = value.type_();
+
+                T::from_value_ref(value).ok_or_else(|| GetError::ValueGetError {
+                    name: name,
+                    error: ValueTypeMismatchError {
+                        expected: T::g_type(),
+                        actual: actual,
+                    },
+                })
+            }
+        }
     }
 
     pub fn get_value<'a>(&'a self, name: &str) -> Option<ValueRef<'a>> {
@@ -188,14 +291,24 @@ impl Structure {
         }
     }
 
-    pub fn set<T: Into<Value>>(&mut self, name: &str, value: T) {
-        unsafe {
-            let name_cstr = CString::new(name).unwrap();
-            let mut gvalue = value.into().into_raw();
+    pub fn set<T: SendValue>(&mut self, name: &str, value: T) {
+        unsafe { self.set_unchecked(name, value) }
+    }
 
-            gst::gst_structure_take_value(&mut self.0, name_cstr.as_ptr(), &mut gvalue);
-            mem::forget(gvalue);
-        }
+    /// Like [`set`](#method.set), but accepts any `Into<Value>` rather
+    /// than requiring `SendValue`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure `value` doesn't carry data that isn't safe
+    /// to send to another thread, as `Structure`'s `Send`/`Sync` impls
+    /// rely on every stored field meeting that bar (see `SendValue`).
+    pub unsafe fn set_unchecked<T: Into<Value>>(&mut self, name: &str, value: T) {
+        let name_cstr = CString::new(name).unwrap();
+        let mut gvalue = value.into().into_raw();
+
+        gst::gst_structure_take_value(&mut self.0, name_cstr.as_ptr(), &mut gvalue);
+        mem::forget(gvalue);
     }
 
     pub fn get_name(&self) -> &str {
@@ -249,7 +362,71 @@ impl Structure {
         unsafe { gst::gst_structure_n_fields(&self.0) as u32 }
     }
 
-    // TODO: Various operations
+    pub fn set_name(&mut self, name: &str) {
+        unsafe {
+            let name_cstr = CString::new(name).unwrap();
+            gst::gst_structure_set_name(&mut self.0, name_cstr.as_ptr());
+        }
+    }
+
+    pub fn has_name(&self, name: &str) -> bool {
+        unsafe {
+            let name_cstr = CString::new(name).unwrap();
+            gst::gst_structure_has_name(&self.0, name_cstr.as_ptr()) == glib::GTRUE
+        }
+    }
+
+    /// Calls `func` with every field of the structure, replacing its value
+    /// with whatever `func` leaves in the passed `ValueRef`. When `func`
+    /// returns `false`, iteration stops and the remaining fields are not
+    /// visited; no field is removed.
+    pub fn map_in_place<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&str, &mut ValueRef) -> bool,
+    {
+        unsafe extern "C" fn trampoline<F>(
+            field_id: glib::GQuark,
+            value: *mut glib::GValue,
+            user_data: glib::gpointer,
+        ) -> glib::gboolean
+        where
+            F: FnMut(&str, &mut ValueRef) -> bool,
+        {
+            let func = &mut *(user_data as *mut F);
+            let name_cstr = CStr::from_ptr(glib::g_quark_to_string(field_id));
+            let name = name_cstr.to_str().unwrap();
+            let mut value_ref = ValueRef::from_ptr(value as *const glib::GValue).unwrap();
+
+            if func(name, &mut value_ref) {
+                glib::GTRUE
+            } else {
+                glib::GFALSE
+            }
+        }
+
+        unsafe {
+            gst::gst_structure_map_in_place(
+                &mut self.0,
+                Some(trampoline::<F>),
+                &mut func as *mut F as glib::gpointer,
+            );
+        }
+    }
+
+    pub fn is_subset(&self, superset: &Structure) -> bool {
+        unsafe { gst::gst_structure_is_subset(&self.0, &superset.0) == glib::GTRUE }
+    }
+
+    pub fn intersect(&self, other: &Structure) -> Option<OwnedStructure> {
+        unsafe {
+            let ptr = gst::gst_structure_intersect(&self.0, &other.0);
+            if ptr.is_null() {
+                None
+            } else {
+                Some(OwnedStructure(ptr as *mut Structure, PhantomData))
+            }
+        }
+    }
 }
 
 impl fmt::Debug for Structure {
@@ -411,4 +588,121 @@ mod tests {
         );
         assert_eq!(s, s2);
     }
+
+    #[test]
+    fn builder() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let s = OwnedStructure::builder("test")
+            .field("f1", "abc")
+            .field("f2", String::from("bcd"))
+            .field("f3", 123i32)
+            .build();
+
+        assert_eq!(s.get_name(), "test");
+        assert_eq!(s.get::<&str>("f1").unwrap().get(), "abc");
+        assert_eq!(s.get::<&str>("f2").unwrap().get(), "bcd");
+        assert_eq!(s.get::<i32>("f3").unwrap().get(), 123i32);
+    }
+
+    #[test]
+    fn get_typed_errors() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let mut s = OwnedStructure::new_empty("test");
+        s.set("f1", "abc");
+
+        assert_eq!(s.get_typed::<&str>("f1").unwrap(), "abc");
+
+        match s.get_typed::<i32>("f1") {
+            Err(GetError::ValueGetError { name: "f1", .. }) => (),
+            other => panic!("expected a ValueGetError, got {:?}", other),
+        }
+
+        match s.get_typed::<&str>("f2") {
+            Err(GetError::FieldNotFound { name: "f2" }) => (),
+            other => panic!("expected a FieldNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fraction_field() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let mut s = OwnedStructure::new_empty("video/x-raw");
+        s.set("framerate", Fraction::new(30, 1));
+
+        assert_eq!(s.get::<Fraction>("framerate").unwrap().get(), Fraction::new(30, 1));
+    }
+
+    #[test]
+    fn send_across_threads() {
+        use std::thread;
+
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let s = OwnedStructure::new(
+            "test",
+            &[("f1", "abc".into()), ("f2", 123i32.into())],
+        );
+
+        let s = thread::spawn(move || {
+            assert_eq!(s.get::<&str>("f1").unwrap().get(), "abc");
+            s
+        }).join()
+            .unwrap();
+
+        assert_eq!(s.get::<i32>("f2").unwrap().get(), 123i32);
+    }
+
+    #[test]
+    fn name_operations() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let mut s = OwnedStructure::new_empty("video/x-raw");
+        assert!(s.has_name("video/x-raw"));
+
+        s.set_name("audio/x-raw");
+        assert!(s.has_name("audio/x-raw"));
+        assert!(!s.has_name("video/x-raw"));
+    }
+
+    #[test]
+    fn map_in_place_fixates() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let mut s = OwnedStructure::new_empty("test");
+        s.set("f1", 1i32);
+        s.set("f2", 2i32);
+
+        s.map_in_place(|name, value| {
+            if name == "f1" {
+                unsafe { value.replace(42i32) };
+            }
+            true
+        });
+
+        assert_eq!(s.get::<i32>("f1").unwrap().get(), 42i32);
+        assert_eq!(s.get::<i32>("f2").unwrap().get(), 2i32);
+    }
+
+    #[test]
+    fn subset_and_intersect() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let sub = OwnedStructure::builder("test").field("f1", 1i32).build();
+        let sup = OwnedStructure::builder("test")
+            .field("f1", 1i32)
+            .field("f2", 2i32)
+            .build();
+
+        assert!(sub.is_subset(&sup));
+        assert!(!sup.is_subset(&sub));
+
+        // `intersect` copies `self` and unions in fields from `other` that
+        // aren't already present, so intersecting the subset with the
+        // superset yields the superset's fields, not the subset's.
+        let intersection = sub.intersect(&sup).unwrap();
+        assert_eq!(intersection, sup);
+    }
 }
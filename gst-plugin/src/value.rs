@@ -0,0 +1,450 @@
+// Copyright (C) 2016-2017 Sebastian Dröge <sebastian@centricular.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::cmp::Ordering;
+use std::ops::Mul;
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+use glib;
+use gst;
+
+pub struct Value(pub(crate) glib::GValue);
+
+impl Value {
+    pub fn new<T: Into<Value>>(value: T) -> Value {
+        value.into()
+    }
+
+    pub fn from_value_ref(value: &ValueRef) -> Value {
+        unsafe {
+            let mut gvalue: glib::GValue = mem::zeroed();
+            glib::g_value_init(&mut gvalue, glib::g_value_type(value.0));
+            glib::g_value_copy(value.0, &mut gvalue);
+
+            Value(gvalue)
+        }
+    }
+
+    pub unsafe fn into_raw(self) -> glib::GValue {
+        let gvalue = self.0;
+        mem::forget(self);
+
+        gvalue
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        Value::from_value_ref(&ValueRef(&self.0, PhantomData))
+    }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) {
+        unsafe { glib::g_value_unset(&mut self.0) }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&ValueRef(&self.0, PhantomData).to_string())
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        ValueRef(&self.0, PhantomData) == ValueRef(&other.0, PhantomData)
+    }
+}
+
+impl Eq for Value {}
+
+impl<'a> From<&'a str> for Value {
+    fn from(v: &'a str) -> Value {
+        unsafe {
+            let mut gvalue: glib::GValue = mem::zeroed();
+            glib::g_value_init(&mut gvalue, glib::G_TYPE_STRING);
+            let cstr = CString::new(v).unwrap();
+            glib::g_value_set_string(&mut gvalue, cstr.as_ptr());
+
+            Value(gvalue)
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Value {
+        Value::from(v.as_str())
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Value {
+        unsafe {
+            let mut gvalue: glib::GValue = mem::zeroed();
+            glib::g_value_init(&mut gvalue, glib::G_TYPE_INT);
+            glib::g_value_set_int(&mut gvalue, v);
+
+            Value(gvalue)
+        }
+    }
+}
+
+/// Marker trait for types that can be turned into a [`Value`](struct.Value.html)
+/// which is safe to send to another thread.
+///
+/// # Safety
+///
+/// Implementors must guarantee that the `Value` produced by `Into<Value>`
+/// owns all of its data (no borrowed pointers into thread-local state) and
+/// that the underlying `GValue`'s boxed/refcounted contents, if any, are
+/// themselves safe to move across threads. This is what lets
+/// [`Structure`](../structure/struct.Structure.html) be `Send`/`Sync`: as
+/// long as every field was inserted through a `SendValue`, the structure as
+/// a whole can be sent.
+pub unsafe trait SendValue: Into<Value> {}
+
+unsafe impl<'a> SendValue for &'a str {}
+unsafe impl SendValue for String {}
+unsafe impl SendValue for i32 {}
+unsafe impl SendValue for bool {}
+unsafe impl SendValue for Fraction {}
+
+/// A type-erased [`Value`](struct.Value.html) that is statically known to
+/// be `SendValue`, because the only way to produce one is through a
+/// `SendValue` source.
+///
+/// This is what lets [`OwnedStructure::new`](../structure/struct.OwnedStructure.html#method.new)
+/// take a slice of heterogeneously-typed fields (like the plain `Value` it
+/// used to take) without reopening the hole a blanket `SendValue for Value`
+/// impl would: unlike `Value`, there's no safe way to conjure a
+/// `SendableValue` out of an arbitrary, possibly non-sendable `ValueRef`.
+#[derive(Clone)]
+pub struct SendableValue(Value);
+
+impl<T: SendValue> From<T> for SendableValue {
+    fn from(v: T) -> SendableValue {
+        SendableValue(v.into())
+    }
+}
+
+impl From<SendableValue> for Value {
+    fn from(v: SendableValue) -> Value {
+        v.0
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Value {
+        unsafe {
+            let mut gvalue: glib::GValue = mem::zeroed();
+            glib::g_value_init(&mut gvalue, glib::G_TYPE_BOOLEAN);
+            glib::g_value_set_boolean(&mut gvalue, if v { glib::GTRUE } else { glib::GFALSE });
+
+            Value(gvalue)
+        }
+    }
+}
+
+/// A fraction, as used for e.g. `framerate` or `pixel-aspect-ratio` fields.
+///
+/// Fractions are always kept normalized: reduced to lowest terms with a
+/// positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fraction(i32, i32);
+
+impl Fraction {
+    pub fn new(numer: i32, denom: i32) -> Fraction {
+        assert_ne!(denom, 0);
+        assert_ne!(
+            numer,
+            i32::min_value(),
+            "Fraction numerator must not be i32::MIN"
+        );
+        assert_ne!(
+            denom,
+            i32::min_value(),
+            "Fraction denominator must not be i32::MIN"
+        );
+
+        let (numer, denom) = if denom < 0 {
+            (-numer, -denom)
+        } else {
+            (numer, denom)
+        };
+
+        let d = gcd(numer.abs(), denom);
+        if d == 0 {
+            Fraction(0, 1)
+        } else {
+            Fraction(numer / d, denom / d)
+        }
+    }
+
+    pub fn numer(&self) -> i32 {
+        self.0
+    }
+
+    pub fn denom(&self) -> i32 {
+        self.1
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.0, self.1)
+    }
+}
+
+impl Mul for Fraction {
+    type Output = Fraction;
+
+    fn mul(self, other: Fraction) -> Fraction {
+        Fraction::new(self.0 * other.0, self.1 * other.1)
+    }
+}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Fraction) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fraction {
+    fn cmp(&self, other: &Fraction) -> Ordering {
+        let a = (self.0 as i64) * (other.1 as i64);
+        let b = (other.0 as i64) * (self.1 as i64);
+
+        a.cmp(&b)
+    }
+}
+
+impl From<Fraction> for Value {
+    fn from(v: Fraction) -> Value {
+        unsafe {
+            let mut gvalue: glib::GValue = mem::zeroed();
+            glib::g_value_init(&mut gvalue, gst::gst_fraction_get_type());
+            gst::gst_value_set_fraction(&mut gvalue, v.0, v.1);
+
+            Value(gvalue)
+        }
+    }
+}
+
+impl<'a> ValueType<'a> for Fraction {
+    fn g_type() -> glib::GType {
+        unsafe { gst::gst_fraction_get_type() }
+    }
+
+    fn from_value_ref(value: ValueRef<'a>) -> Option<Fraction> {
+        unsafe {
+            if glib::g_value_type(value.0) != Self::g_type() {
+                return None;
+            }
+
+            let numer = gst::gst_value_get_fraction_numerator(value.0);
+            let denom = gst::gst_value_get_fraction_denominator(value.0);
+
+            Some(Fraction::new(numer, denom))
+        }
+    }
+}
+
+pub struct ValueRef<'a>(pub(crate) *const glib::GValue, pub(crate) PhantomData<&'a ()>);
+
+impl<'a> ValueRef<'a> {
+    pub unsafe fn from_ptr(ptr: *const glib::GValue) -> Option<ValueRef<'a>> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(ValueRef(ptr, PhantomData))
+        }
+    }
+
+    pub fn type_(&self) -> glib::GType {
+        unsafe { glib::g_value_type(self.0) }
+    }
+
+    /// Replaces the pointed-to `GValue` in place.
+    ///
+    /// # Safety
+    ///
+    /// `self` must have been obtained from a context that holds a mutable
+    /// borrow of the underlying value, such as
+    /// [`Structure::map_in_place`](../structure/struct.Structure.html#method.map_in_place)
+    /// or [`Caps::get_mut_structure`](../caps/struct.Caps.html#method.get_mut_structure).
+    /// A `ValueRef` obtained from a shared `&Structure` (e.g.
+    /// `Structure::get_value`) may be aliased by other live references to
+    /// the same field, so replacing its value through those would free
+    /// data another reference still points at.
+    pub unsafe fn replace<T: SendValue>(&mut self, value: T) {
+        let dest = self.0 as *mut glib::GValue;
+        glib::g_value_unset(dest);
+
+        let raw = value.into().into_raw();
+        ptr::write(dest, raw);
+    }
+
+    pub fn to_string(&self) -> String {
+        unsafe {
+            let ptr = gst::gst_value_serialize(self.0);
+            let s = ::std::ffi::CStr::from_ptr(ptr).to_str().unwrap().into();
+            glib::g_free(ptr as glib::gpointer);
+
+            s
+        }
+    }
+}
+
+impl<'a> PartialEq for ValueRef<'a> {
+    fn eq(&self, other: &ValueRef<'a>) -> bool {
+        unsafe { gst::gst_value_compare(self.0, other.0) == gst::GST_VALUE_EQUAL }
+    }
+}
+
+pub trait ValueType<'a>: Sized {
+    fn g_type() -> glib::GType;
+    fn from_value_ref(value: ValueRef<'a>) -> Option<Self>;
+}
+
+impl<'a> ValueType<'a> for &'a str {
+    fn g_type() -> glib::GType {
+        glib::G_TYPE_STRING
+    }
+
+    fn from_value_ref(value: ValueRef<'a>) -> Option<&'a str> {
+        unsafe {
+            if glib::g_value_type(value.0) != Self::g_type() {
+                return None;
+            }
+
+            let ptr = glib::g_value_get_string(value.0);
+            if ptr.is_null() {
+                return None;
+            }
+
+            ::std::ffi::CStr::from_ptr(ptr).to_str().ok()
+        }
+    }
+}
+
+impl<'a> ValueType<'a> for i32 {
+    fn g_type() -> glib::GType {
+        glib::G_TYPE_INT
+    }
+
+    fn from_value_ref(value: ValueRef<'a>) -> Option<i32> {
+        unsafe {
+            if glib::g_value_type(value.0) != Self::g_type() {
+                return None;
+            }
+
+            Some(glib::g_value_get_int(value.0))
+        }
+    }
+}
+
+impl<'a> ValueType<'a> for bool {
+    fn g_type() -> glib::GType {
+        glib::G_TYPE_BOOLEAN
+    }
+
+    fn from_value_ref(value: ValueRef<'a>) -> Option<bool> {
+        unsafe {
+            if glib::g_value_type(value.0) != Self::g_type() {
+                return None;
+            }
+
+            Some(glib::g_value_get_boolean(value.0) == glib::GTRUE)
+        }
+    }
+}
+
+pub struct TypedValueRef<'a, T: ValueType<'a>> {
+    value: T,
+    phantom: PhantomData<ValueRef<'a>>,
+}
+
+impl<'a, T: ValueType<'a>> TypedValueRef<'a, T> {
+    pub(crate) fn new(value: T) -> TypedValueRef<'a, T> {
+        TypedValueRef {
+            value: value,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn from_value_ref(value: ValueRef<'a>) -> Option<TypedValueRef<'a, T>> {
+        T::from_value_ref(value).map(TypedValueRef::new)
+    }
+
+    pub fn get(self) -> T {
+        self.value
+    }
+}
+
+/// The value of a field was present but didn't have the type that was
+/// requested for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueTypeMismatchError {
+    pub expected: glib::GType,
+    pub actual: glib::GType,
+}
+
+impl fmt::Display for ValueTypeMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected value of type {:?} but got {:?}",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn new_and_eq() {
+        unsafe { gst::gst_init(ptr::null_mut(), ptr::null_mut()) };
+
+        let a = Value::new("abc");
+        let b = Value::new("abc".to_string());
+        assert_eq!(a, b);
+
+        let c = Value::new(123i32);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn fraction_normalizes_and_compares() {
+        let a = Fraction::new(30, 1);
+        let b = Fraction::new(60, 2);
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "30/1");
+
+        let c = Fraction::new(-1, -2);
+        assert_eq!(c, Fraction::new(1, 2));
+
+        assert!(Fraction::new(1, 2) < Fraction::new(2, 3));
+        assert_eq!(Fraction::new(1, 2) * Fraction::new(2, 3), Fraction::new(1, 3));
+    }
+}